@@ -4,12 +4,83 @@ use pgx::JsonB;
 use reqwest::{self, header, Url};
 use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
 use reqwest_retry::{policies::ExponentialBackoff, RetryTransientMiddleware};
+use pgx::pg_extern;
 use serde_json::Value as JsonValue;
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
 use time::OffsetDateTime;
 
 use supabase_wrappers::prelude::*;
 
+// A cache of completed scans, keyed by a hash of the scan shape
+// (object + quals + columns + limit). Repeated reporting queries over the same
+// table reuse these rows instead of re-paginating the Stripe API.
+//
+// NOTE: the store is a process-local `static`, so it is per-Postgres-backend.
+// A DML write or `stripe_fdw_cache_clear` only evicts the calling connection's
+// cache; other backends keep serving their own entries until those expire.
+// Keep `cache_ttl` short if several connections scan the same tables.
+struct CacheEntry {
+    fetched: Instant,
+    object: String,
+    // the canonical scan shape this entry was fetched for, compared on lookup
+    // so a hash collision can never return a different scan's rows
+    shape: String,
+    rows: Vec<Row>,
+}
+
+static SCAN_CACHE: OnceLock<Mutex<HashMap<u64, CacheEntry>>> = OnceLock::new();
+
+fn scan_cache() -> &'static Mutex<HashMap<u64, CacheEntry>> {
+    SCAN_CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+// a stable identifier for the Stripe account behind a foreign server: the API
+// base url plus a hash of the whole key, enough to tell two accounts apart
+// without retaining the secret in the process-wide cache
+fn server_identity(base_url: &str, api_key: Option<&str>) -> String {
+    let mut hasher = DefaultHasher::new();
+    api_key.hash(&mut hasher);
+    format!("{}#{:x}", base_url, hasher.finish())
+}
+
+// the canonical string describing a scan shape; Qual/Limit don't implement
+// Hash or Eq, so we serialize them to compare and hash consistently. The
+// server identity is included so entries can't collide across accounts.
+fn cache_shape(
+    server: &str,
+    obj: &str,
+    quals: &Vec<Qual>,
+    columns: &Vec<String>,
+    limit: &Option<Limit>,
+) -> String {
+    format!("{}|{}|{:?}|{:?}|{:?}", server, obj, quals, columns, limit)
+}
+
+fn cache_key(shape: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    shape.hash(&mut hasher);
+    hasher.finish()
+}
+
+// evict every cached scan for the given object, e.g. after a DML write
+fn invalidate_cache(obj: &str) {
+    if let Ok(mut cache) = scan_cache().lock() {
+        cache.retain(|_, entry| entry.object != obj);
+    }
+}
+
+// Explicitly clear all cached scans for an object. Useful when rows were
+// changed outside of Postgres (e.g. in the Stripe dashboard). Only clears the
+// calling backend's cache — see the note on `CacheEntry`.
+#[pg_extern]
+fn stripe_fdw_cache_clear(object: &str) {
+    invalidate_cache(object);
+}
+
 fn create_client(api_key: &str) -> ClientWithMiddleware {
     let mut headers = header::HeaderMap::new();
     let value = format!("Bearer {}", api_key);
@@ -26,10 +97,23 @@ fn create_client(api_key: &str) -> ClientWithMiddleware {
         .build()
 }
 
+// walk a dotted source path (e.g. "payment_method_details.card.last4") step by
+// step, returning None if any segment is missing or null
+fn json_by_path<'a>(obj: &'a JsonValue, source_path: &str) -> Option<&'a JsonValue> {
+    let mut cur = obj;
+    for segment in source_path.split('.') {
+        cur = cur
+            .as_object()
+            .and_then(|v| v.get(segment))
+            .filter(|v| !v.is_null())?;
+    }
+    Some(cur)
+}
+
 fn extract_to_rows(
     resp_body: &str,
     obj_key: &str,
-    common_cols: Vec<(&str, &str)>,
+    common_cols: Vec<(&str, &str, &str)>,
     tgt_cols: &Vec<String>,
 ) -> (Vec<Row>, Option<String>, Option<bool>) {
     let mut result = Vec::new();
@@ -45,22 +129,31 @@ fn extract_to_rows(
         let mut row = Row::new();
 
         // extract common columns
-        for (col_name, col_type) in &common_cols {
-            if tgt_cols.iter().any(|c| c == col_name) {
-                let cell = obj
-                    .as_object()
-                    .and_then(|v| v.get(*col_name))
-                    .and_then(|v| match *col_type {
-                        "i64" => v.as_i64().map(|a| Cell::I64(a)),
-                        "string" => v.as_str().map(|a| Cell::String(a.to_owned())),
-                        "timestamp" => v.as_i64().map(|a| {
-                            let dt = OffsetDateTime::from_unix_timestamp(a).unwrap();
-                            let ts = Timestamp::try_from(dt).unwrap();
-                            Cell::Timestamp(ts)
-                        }),
-                        _ => None,
-                    });
-                row.push(col_name, cell);
+        for (source_path, target_col, col_type) in &common_cols {
+            if tgt_cols.iter().any(|c| c == target_col) {
+                let cell = json_by_path(obj, source_path).and_then(|v| match *col_type {
+                    "bool" => v.as_bool().map(|a| Cell::Bool(a)),
+                    "i64" => v.as_i64().map(|a| Cell::I64(a)),
+                    "f64" => v.as_f64().map(|a| Cell::F64(a)),
+                    "string" => v.as_str().map(|a| Cell::String(a.to_owned())),
+                    "timestamp" => v.as_i64().map(|a| {
+                        let dt = OffsetDateTime::from_unix_timestamp(a).unwrap();
+                        let ts = Timestamp::try_from(dt).unwrap();
+                        Cell::Timestamp(ts)
+                    }),
+                    // expandable references are either a bare id string or, when
+                    // requested with expand[], a nested object carrying an 'id'
+                    "expandable" => v
+                        .as_str()
+                        .or_else(|| {
+                            v.as_object()
+                                .and_then(|o| o.get("id"))
+                                .and_then(|id| id.as_str())
+                        })
+                        .map(|a| Cell::String(a.to_owned())),
+                    _ => None,
+                });
+                row.push(target_col, cell);
             }
         }
 
@@ -91,6 +184,40 @@ fn extract_to_rows(
     (result, cursor, has_more)
 }
 
+// serialize a row's non-null cells into form body pairs for the Stripe API,
+// which expects `application/x-www-form-urlencoded` rather than JSON
+fn body_from_row(row: &Row) -> Vec<(String, String)> {
+    let mut params = Vec::new();
+    for (col, cell) in row.cols.iter().zip(row.cells.iter()) {
+        // the 'attrs' blob and the 'id' primary key are not writable fields:
+        // Stripe rejects an `id` form parameter as unknown, and on UPDATE the
+        // row carries the existing id which must go in the URL, not the body
+        if col == "attrs" || col == "id" {
+            continue;
+        }
+        if let Some(cell) = cell {
+            if let Some(value) = cell_to_param(cell) {
+                params.push((col.to_owned(), value));
+            }
+        }
+    }
+    params
+}
+
+// render a single cell as the string Stripe's form API expects
+fn cell_to_param(cell: &Cell) -> Option<String> {
+    match cell {
+        Cell::Bool(v) => Some(v.to_string()),
+        Cell::F64(v) => Some(v.to_string()),
+        Cell::I64(v) => Some(v.to_string()),
+        Cell::String(v) => Some(v.to_owned()),
+        Cell::Timestamp(v) => OffsetDateTime::try_from(*v)
+            .ok()
+            .map(|dt| dt.unix_timestamp().to_string()),
+        _ => None,
+    }
+}
+
 fn pushdown_quals(url: &mut Url, quals: &Vec<Qual>, fields: Vec<&str>) {
     for qual in quals {
         for field in &fields {
@@ -109,6 +236,38 @@ fn pushdown_quals(url: &mut Url, quals: &Vec<Qual>, fields: Vec<&str>) {
     }
 }
 
+// translate range/comparison quals on timestamp and integer fields into
+// Stripe's bracketed filter syntax, e.g. `created[gte]=...`, `amount[lt]=...`
+// ref: https://stripe.com/docs/api/charges/list
+fn pushdown_range_quals(url: &mut Url, quals: &Vec<Qual>, fields: Vec<&str>) {
+    for qual in quals {
+        for field in &fields {
+            if qual.field == *field && !qual.use_or {
+                let suffix = match qual.operator.as_str() {
+                    ">" => "gt",
+                    ">=" => "gte",
+                    "<" => "lt",
+                    "<=" => "lte",
+                    _ => continue,
+                };
+                if let Value::Cell(cell) = &qual.value {
+                    let value = match cell {
+                        Cell::I64(v) => Some(v.to_string()),
+                        Cell::Timestamp(v) => OffsetDateTime::try_from(*v)
+                            .ok()
+                            .map(|dt| dt.unix_timestamp().to_string()),
+                        _ => None,
+                    };
+                    if let Some(value) = value {
+                        url.query_pairs_mut()
+                            .append_pair(&format!("{}[{}]", field, suffix), &value);
+                    }
+                }
+            }
+        }
+    }
+}
+
 #[wrappers_meta(
     version = "0.1.1",
     author = "Supabase",
@@ -119,6 +278,12 @@ pub(crate) struct StripeFdw {
     base_url: Url,
     client: Option<ClientWithMiddleware>,
     scan_result: Option<Vec<Row>>,
+    obj: Option<String>,
+    readonly: bool,
+    cache_ttl: Option<u64>,
+    // identifies the Stripe account this wrapper talks to, so cache entries
+    // can't be shared across foreign servers pointing at different accounts
+    server_id: String,
 }
 
 impl StripeFdw {
@@ -127,18 +292,22 @@ impl StripeFdw {
             .get("api_url")
             .map(|t| t.to_owned())
             .unwrap_or("https://api.stripe.com/v1/".to_string());
-        let client = match options.get("api_key") {
-            Some(api_key) => Some(create_client(&api_key)),
+        let api_key = match options.get("api_key") {
+            Some(api_key) => Some(api_key.to_owned()),
             None => require_option("api_key_id", options)
-                .and_then(|key_id| get_vault_secret(&key_id))
-                .and_then(|api_key| Some(create_client(&api_key))),
+                .and_then(|key_id| get_vault_secret(&key_id)),
         };
+        let client = api_key.as_ref().map(|key| create_client(key));
 
         StripeFdw {
             rt: create_async_runtime(),
+            server_id: server_identity(&base_url, api_key.as_deref()),
             base_url: Url::parse(&base_url).unwrap(),
             client,
             scan_result: None,
+            obj: None,
+            readonly: false,
+            cache_ttl: options.get("cache_ttl").and_then(|v| v.parse().ok()),
         }
     }
 
@@ -155,12 +324,14 @@ impl StripeFdw {
         // ref: https://stripe.com/docs/api/balance_transactions/list
         if obj == "balance_transactions" {
             pushdown_quals(&mut url, quals, vec!["payout", "type"]);
+            pushdown_range_quals(&mut url, quals, vec!["created"]);
         }
 
         // pushdown quals for charges
         // ref: https://stripe.com/docs/api/charges/list
         if obj == "charges" {
             pushdown_quals(&mut url, quals, vec!["customer"]);
+            pushdown_range_quals(&mut url, quals, vec!["created"]);
         }
 
         // pushdown quals for customers
@@ -173,12 +344,14 @@ impl StripeFdw {
         // ref: https://stripe.com/docs/api/invoices/list
         if obj == "invoices" {
             pushdown_quals(&mut url, quals, vec!["customer", "status", "subscription"]);
+            pushdown_range_quals(&mut url, quals, vec!["created", "due_date"]);
         }
 
         // pushdown quals for payment intents
         // ref: https://stripe.com/docs/api/payment_intents/list
         if obj == "payment_intents" {
             pushdown_quals(&mut url, quals, vec!["customer"]);
+            pushdown_range_quals(&mut url, quals, vec!["created"]);
         }
 
         // pushdown quals for subscriptions
@@ -210,22 +383,26 @@ impl StripeFdw {
             "balance" => extract_to_rows(
                 resp_body,
                 "available",
-                vec![("amount", "i64"), ("currency", "string")],
+                vec![
+                    ("amount", "amount", "i64"),
+                    ("currency", "currency", "string"),
+                ],
                 tgt_cols,
             ),
             "balance_transactions" => extract_to_rows(
                 resp_body,
                 "data",
                 vec![
-                    ("id", "string"),
-                    ("amount", "i64"),
-                    ("currency", "string"),
-                    ("description", "string"),
-                    ("fee", "i64"),
-                    ("net", "i64"),
-                    ("status", "string"),
-                    ("type", "string"),
-                    ("created", "timestamp"),
+                    ("id", "id", "string"),
+                    ("amount", "amount", "i64"),
+                    ("currency", "currency", "string"),
+                    ("description", "description", "string"),
+                    ("fee", "fee", "i64"),
+                    ("net", "net", "i64"),
+                    ("exchange_rate", "exchange_rate", "f64"),
+                    ("status", "status", "string"),
+                    ("type", "type", "string"),
+                    ("created", "created", "timestamp"),
                 ],
                 tgt_cols,
             ),
@@ -233,36 +410,50 @@ impl StripeFdw {
                 resp_body,
                 "data",
                 vec![
-                    ("id", "string"),
-                    ("amount", "i64"),
-                    ("currency", "string"),
-                    ("customer", "string"),
-                    ("description", "string"),
-                    ("invoice", "string"),
-                    ("payment_intent", "string"),
-                    ("status", "string"),
-                    ("created", "timestamp"),
+                    ("id", "id", "string"),
+                    ("amount", "amount", "i64"),
+                    ("currency", "currency", "string"),
+                    ("customer", "customer", "string"),
+                    ("description", "description", "string"),
+                    ("invoice", "invoice", "string"),
+                    ("payment_intent", "payment_intent", "string"),
+                    ("payment_method", "payment_method", "expandable"),
+                    // nested + renamed: flatten the card's last4 digits out of
+                    // the payment_method_details sub-object
+                    ("payment_method_details.card.last4", "card_last4", "string"),
+                    ("status", "status", "string"),
+                    ("paid", "paid", "bool"),
+                    ("refunded", "refunded", "bool"),
+                    ("created", "created", "timestamp"),
                 ],
                 tgt_cols,
             ),
             "customers" => extract_to_rows(
                 resp_body,
                 "data",
-                vec![("id", "string"), ("email", "string")],
+                vec![
+                    ("id", "id", "string"),
+                    ("email", "email", "string"),
+                    ("balance", "balance", "i64"),
+                    ("delinquent", "delinquent", "bool"),
+                    ("livemode", "livemode", "bool"),
+                ],
                 tgt_cols,
             ),
             "invoices" => extract_to_rows(
                 resp_body,
                 "data",
                 vec![
-                    ("id", "string"),
-                    ("customer", "string"),
-                    ("subscription", "string"),
-                    ("status", "string"),
-                    ("total", "i64"),
-                    ("currency", "string"),
-                    ("period_start", "timestamp"),
-                    ("period_end", "timestamp"),
+                    ("id", "id", "string"),
+                    ("customer", "customer", "string"),
+                    ("subscription", "subscription", "string"),
+                    ("status", "status", "string"),
+                    ("paid", "paid", "bool"),
+                    ("attempted", "attempted", "bool"),
+                    ("total", "total", "i64"),
+                    ("currency", "currency", "string"),
+                    ("period_start", "period_start", "timestamp"),
+                    ("period_end", "period_end", "timestamp"),
                 ],
                 tgt_cols,
             ),
@@ -270,12 +461,12 @@ impl StripeFdw {
                 resp_body,
                 "data",
                 vec![
-                    ("id", "string"),
-                    ("customer", "string"),
-                    ("amount", "i64"),
-                    ("currency", "string"),
-                    ("payment_method", "string"),
-                    ("created", "timestamp"),
+                    ("id", "id", "string"),
+                    ("customer", "customer", "string"),
+                    ("amount", "amount", "i64"),
+                    ("currency", "currency", "string"),
+                    ("payment_method", "payment_method", "expandable"),
+                    ("created", "created", "timestamp"),
                 ],
                 tgt_cols,
             ),
@@ -283,11 +474,11 @@ impl StripeFdw {
                 resp_body,
                 "data",
                 vec![
-                    ("id", "string"),
-                    ("customer", "string"),
-                    ("currency", "string"),
-                    ("current_period_start", "timestamp"),
-                    ("current_period_end", "timestamp"),
+                    ("id", "id", "string"),
+                    ("customer", "customer", "string"),
+                    ("currency", "currency", "string"),
+                    ("current_period_start", "current_period_start", "timestamp"),
+                    ("current_period_end", "current_period_end", "timestamp"),
                 ],
                 tgt_cols,
             ),
@@ -312,6 +503,16 @@ macro_rules! report_fetch_error {
     }};
 }
 
+macro_rules! report_request_error {
+    ($err:ident) => {{
+        report_error(
+            PgSqlErrorCode::ERRCODE_FDW_ERROR,
+            &format!("request failed: {}", $err),
+        );
+        return;
+    }};
+}
+
 impl ForeignDataWrapper for StripeFdw {
     fn begin_scan(
         &mut self,
@@ -327,6 +528,26 @@ impl ForeignDataWrapper for StripeFdw {
             return;
         };
 
+        // a table-level `cache_ttl` overrides the server-level default
+        let cache_ttl = options
+            .get("cache_ttl")
+            .and_then(|v| v.parse().ok())
+            .or(self.cache_ttl);
+        let shape = cache_shape(&self.server_id, &obj, quals, columns, limit);
+        let key = cache_key(&shape);
+
+        // serve from the cache when a fresh entry for this exact shape exists
+        if let Some(ttl) = cache_ttl {
+            if let Ok(cache) = scan_cache().lock() {
+                if let Some(entry) = cache.get(&key) {
+                    if entry.shape == shape && entry.fetched.elapsed().as_secs() < ttl {
+                        self.scan_result = Some(entry.rows.clone());
+                        return;
+                    }
+                }
+            }
+        }
+
         if let Some(client) = &self.client {
             let page_size = 100; // maximum page size limit for Stripe API
             let page_cnt = if let Some(limit) = limit {
@@ -376,6 +597,24 @@ impl ForeignDataWrapper for StripeFdw {
                 page += 1;
             }
 
+            // persist the completed scan for reuse within its TTL window
+            if let Some(ttl) = cache_ttl {
+                if let Ok(mut cache) = scan_cache().lock() {
+                    // drop entries that have outlived the current TTL so the
+                    // cache doesn't grow unbounded across distinct scan shapes
+                    cache.retain(|_, entry| entry.fetched.elapsed().as_secs() < ttl);
+                    cache.insert(
+                        key,
+                        CacheEntry {
+                            fetched: Instant::now(),
+                            object: obj.clone(),
+                            shape: shape.clone(),
+                            rows: result.clone(),
+                        },
+                    );
+                }
+            }
+
             self.scan_result = Some(result);
         }
     }
@@ -392,4 +631,236 @@ impl ForeignDataWrapper for StripeFdw {
     fn end_scan(&mut self) {
         self.scan_result.take();
     }
+
+    // NOTE on RETURNING: this interface version hands the modify callbacks the
+    // row immutably (`insert(&Row)`, `update(_, &Row)`), so there is no channel
+    // to write the object Stripe returns (the generated `id`, `created`, ...)
+    // back into the tuple. RETURNING therefore reflects only the values
+    // Postgres supplied in the statement, not server-generated fields. Parsing
+    // the response would be dead work until the interface exposes a writeback
+    // row, so we don't.
+    fn begin_modify(&mut self, options: &HashMap<String, String>) {
+        self.obj = require_option("object", options);
+        self.readonly = options
+            .get("readonly")
+            .map(|v| v == "true")
+            .unwrap_or(false);
+    }
+
+    fn insert(&mut self, src: &Row) {
+        if self.readonly {
+            report_error(
+                PgSqlErrorCode::ERRCODE_FDW_ERROR,
+                "cannot modify a read-only Stripe table",
+            );
+            return;
+        }
+
+        let obj = match &self.obj {
+            Some(obj) => obj.clone(),
+            None => return,
+        };
+
+        if let Some(client) = &self.client {
+            let url = self.base_url.join(&obj).unwrap();
+            let body = body_from_row(src);
+
+            // supabase-wrappers takes `src` immutably, so there is no channel to
+            // write Stripe-assigned fields back: RETURNING reflects only the
+            // Postgres-supplied values, not the generated `id`/`created`.
+            match self.rt.block_on(client.post(url).form(&body).send()) {
+                Ok(resp) => match resp.error_for_status() {
+                    Ok(_) => invalidate_cache(&obj),
+                    Err(err) => report_request_error!(err),
+                },
+                Err(err) => report_request_error!(err),
+            }
+        }
+    }
+
+    fn update(&mut self, rowid: &Cell, new_row: &Row) {
+        if self.readonly {
+            report_error(
+                PgSqlErrorCode::ERRCODE_FDW_ERROR,
+                "cannot modify a read-only Stripe table",
+            );
+            return;
+        }
+
+        let obj = match &self.obj {
+            Some(obj) => obj.clone(),
+            None => return,
+        };
+
+        if let Some(client) = &self.client {
+            let id = match rowid {
+                Cell::String(id) => id,
+                _ => {
+                    report_error(
+                        PgSqlErrorCode::ERRCODE_FDW_ERROR,
+                        "unexpected rowid type, expected a text id",
+                    );
+                    return;
+                }
+            };
+            let url = self.base_url.join(&format!("{}/", obj)).unwrap();
+            let url = url.join(id).unwrap();
+            let body = body_from_row(new_row);
+
+            match self.rt.block_on(client.post(url).form(&body).send()) {
+                Ok(resp) => match resp.error_for_status() {
+                    Ok(_) => invalidate_cache(&obj),
+                    Err(err) => report_request_error!(err),
+                },
+                Err(err) => report_request_error!(err),
+            }
+        }
+    }
+
+    fn delete(&mut self, rowid: &Cell) {
+        if self.readonly {
+            report_error(
+                PgSqlErrorCode::ERRCODE_FDW_ERROR,
+                "cannot modify a read-only Stripe table",
+            );
+            return;
+        }
+
+        let obj = match &self.obj {
+            Some(obj) => obj.clone(),
+            None => return,
+        };
+
+        if let Some(client) = &self.client {
+            let id = match rowid {
+                Cell::String(id) => id,
+                _ => {
+                    report_error(
+                        PgSqlErrorCode::ERRCODE_FDW_ERROR,
+                        "unexpected rowid type, expected a text id",
+                    );
+                    return;
+                }
+            };
+            let url = self.base_url.join(&format!("{}/", obj)).unwrap();
+            let url = url.join(id).unwrap();
+
+            match self.rt.block_on(client.delete(url).send()) {
+                Ok(resp) => match resp.error_for_status() {
+                    Ok(_) => invalidate_cache(&obj),
+                    Err(err) => report_request_error!(err),
+                },
+                Err(err) => report_request_error!(err),
+            }
+        }
+    }
+
+    fn end_modify(&mut self) {
+        self.obj.take();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn json_by_path_walks_nested_objects() {
+        let value = json!({
+            "payment_method_details": { "card": { "last4": "4242" } }
+        });
+
+        assert_eq!(
+            json_by_path(&value, "payment_method_details.card.last4")
+                .and_then(|v| v.as_str()),
+            Some("4242")
+        );
+        // a top-level key is still reachable (backward compatible)
+        assert!(json_by_path(&value, "payment_method_details").is_some());
+        // a missing segment yields None, which becomes SQL NULL
+        assert!(json_by_path(&value, "payment_method_details.card.country").is_none());
+        assert!(json_by_path(&value, "shipping.address.city").is_none());
+    }
+
+    #[test]
+    fn pushdown_range_quals_emits_bracket_syntax() {
+        let quals = vec![
+            Qual {
+                field: "created".to_owned(),
+                operator: ">=".to_owned(),
+                value: Value::Cell(Cell::I64(1_700_000_000)),
+                use_or: false,
+            },
+            Qual {
+                field: "created".to_owned(),
+                operator: "<".to_owned(),
+                value: Value::Cell(Cell::I64(1_700_086_400)),
+                use_or: false,
+            },
+            // equality is handled by pushdown_quals, not here
+            Qual {
+                field: "created".to_owned(),
+                operator: "=".to_owned(),
+                value: Value::Cell(Cell::I64(1)),
+                use_or: false,
+            },
+        ];
+
+        let mut url = Url::parse("https://api.stripe.com/v1/charges").unwrap();
+        pushdown_range_quals(&mut url, &quals, vec!["created"]);
+
+        let pairs: Vec<(String, String)> = url
+            .query_pairs()
+            .map(|(k, v)| (k.into_owned(), v.into_owned()))
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![
+                ("created[gte]".to_owned(), "1700000000".to_owned()),
+                ("created[lt]".to_owned(), "1700086400".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn body_from_row_skips_id_and_attrs() {
+        let mut row = Row::new();
+        row.push("id", Some(Cell::String("cus_123".to_owned())));
+        row.push("email", Some(Cell::String("a@b.com".to_owned())));
+        row.push("balance", Some(Cell::I64(500)));
+        row.push("description", None);
+        row.push("attrs", Some(Cell::Bool(true)));
+
+        let params = body_from_row(&row);
+
+        // id and attrs are never writable, null cells are dropped
+        assert_eq!(
+            params,
+            vec![
+                ("email".to_owned(), "a@b.com".to_owned()),
+                ("balance".to_owned(), "500".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn cache_shape_separates_accounts() {
+        let quals = Vec::new();
+        let columns = vec!["id".to_owned()];
+        let limit = None;
+
+        let live = server_identity("https://api.stripe.com/v1/", Some("sk_live_abcd"));
+        let test = server_identity("https://api.stripe.com/v1/", Some("sk_test_wxyz"));
+
+        // same object/quals/columns but different accounts must not collide
+        let a = cache_shape(&live, "customers", &quals, &columns, &limit);
+        let b = cache_shape(&test, "customers", &quals, &columns, &limit);
+        assert_ne!(a, b);
+        assert_ne!(cache_key(&a), cache_key(&b));
+
+        // identical shapes hash identically
+        let a2 = cache_shape(&live, "customers", &quals, &columns, &limit);
+        assert_eq!(cache_key(&a), cache_key(&a2));
+    }
 }